@@ -0,0 +1,174 @@
+//! BAI index generation (the UCSC binning scheme + a linear index),
+//! as described in the SAM/BAM spec section 5.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Size, in bits, of a linear-index window (16 kbp).
+const LINEAR_SHIFT: u32 = 14;
+
+/// Computes the bin number for a half-open reference interval `[beg, end)`,
+/// per the standard recurrence from the SAM/BAM spec.
+pub fn reg2bin(beg: i32, end: i32) -> u16 {
+    let end = end - 1;
+    if beg >> 14 == end >> 14 {
+        return (4681 + (beg >> 14)) as u16;
+    }
+    if beg >> 17 == end >> 17 {
+        return (585 + (beg >> 17)) as u16;
+    }
+    if beg >> 20 == end >> 20 {
+        return (73 + (beg >> 20)) as u16;
+    }
+    if beg >> 23 == end >> 23 {
+        return (9 + (beg >> 23)) as u16;
+    }
+    if beg >> 26 == end >> 26 {
+        return (1 + (beg >> 26)) as u16;
+    }
+    0
+}
+
+/// Reference bases consumed by a CIGAR string (M/D/N/=/X), used to derive
+/// the half-open interval `[pos, pos + span)` a record occupies.
+pub fn ref_span(cigar: &str) -> u32 {
+    let mut span = 0u32;
+    let mut num = String::new();
+    for c in cigar.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+        } else {
+            let len: u32 = num.parse().unwrap_or(0);
+            if matches!(c, 'M' | 'D' | 'N' | '=' | 'X') {
+                span += len;
+            }
+            num.clear();
+        }
+    }
+    span
+}
+
+/// Accumulates per-reference bin chunks and linear-index entries while
+/// records are written, then serializes them as a `.bai` file.
+pub struct BaiIndexBuilder {
+    bins: Vec<BTreeMap<u16, Vec<(u64, u64)>>>,
+    linear: Vec<Vec<u64>>,
+    n_no_coor: u64,
+}
+
+impl BaiIndexBuilder {
+    pub fn new(n_ref: usize) -> Self {
+        BaiIndexBuilder {
+            bins: (0..n_ref).map(|_| BTreeMap::new()).collect(),
+            linear: (0..n_ref).map(|_| Vec::new()).collect(),
+            n_no_coor: 0,
+        }
+    }
+
+    /// Records a mapped alignment spanning `[pos, pos + span)` on `tid`,
+    /// occupying virtual offsets `[voffset_beg, voffset_end)`.
+    pub fn add_record(&mut self, tid: usize, pos: i32, span: u32, voffset_beg: u64, voffset_end: u64) {
+        let end = pos + span.max(1) as i32;
+        let bin = reg2bin(pos, end);
+
+        self.bins[tid]
+            .entry(bin)
+            .or_default()
+            .push((voffset_beg, voffset_end));
+
+        let first_window = (pos >> LINEAR_SHIFT) as usize;
+        let last_window = ((end - 1).max(pos) >> LINEAR_SHIFT) as usize;
+        let intervals = &mut self.linear[tid];
+        if intervals.len() <= last_window {
+            intervals.resize(last_window + 1, 0);
+        }
+        for window in &mut intervals[first_window..=last_window] {
+            if *window == 0 || voffset_beg < *window {
+                *window = voffset_beg;
+            }
+        }
+    }
+
+    /// Records a read with no reference coordinate (`RNAME` of `*`).
+    pub fn add_unmapped(&mut self) {
+        self.n_no_coor += 1;
+    }
+
+    /// Rewrites every stored offset from a provisional `(block_index <<
+    /// 16) | local_offset` form into a real virtual offset, using the
+    /// final compressed byte offset of each block. Used when records were
+    /// indexed against a [`crate::bgzf::ParallelBgzfWriter`], whose block
+    /// compressed sizes aren't known until compression completes.
+    pub fn remap_block_offsets(&mut self, block_offsets: &[u64]) {
+        let remap = |v: u64| (block_offsets[(v >> 16) as usize] << 16) | (v & 0xFFFF);
+
+        for bins in &mut self.bins {
+            for chunks in bins.values_mut() {
+                for (beg, end) in chunks {
+                    *beg = remap(*beg);
+                    *end = remap(*end);
+                }
+            }
+        }
+        for intervals in &mut self.linear {
+            for ioffset in intervals {
+                *ioffset = remap(*ioffset);
+            }
+        }
+    }
+
+    /// Writes the accumulated index to `path` in BAI format.
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(b"BAI\x01")?;
+        file.write_all(&(self.bins.len() as i32).to_le_bytes())?;
+
+        for (bins, intervals) in self.bins.iter().zip(self.linear.iter()) {
+            file.write_all(&(bins.len() as i32).to_le_bytes())?;
+            for (bin, chunks) in bins {
+                file.write_all(&(*bin as u32).to_le_bytes())?;
+                file.write_all(&(chunks.len() as i32).to_le_bytes())?;
+                for (chunk_beg, chunk_end) in chunks {
+                    file.write_all(&chunk_beg.to_le_bytes())?;
+                    file.write_all(&chunk_end.to_le_bytes())?;
+                }
+            }
+
+            file.write_all(&(intervals.len() as i32).to_le_bytes())?;
+            for ioffset in intervals {
+                file.write_all(&ioffset.to_le_bytes())?;
+            }
+        }
+
+        file.write_all(&self.n_no_coor.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reg2bin_within_one_window_uses_the_finest_bin() {
+        assert_eq!(reg2bin(100, 200), 4681);
+        assert_eq!(reg2bin(0, 1), 4681);
+    }
+
+    #[test]
+    fn reg2bin_widens_as_the_interval_crosses_windows() {
+        // Crosses a 16 kbp boundary but stays within one 128 kbp window.
+        assert_eq!(reg2bin(0, 100_000), 585);
+        // Spans the whole genome, landing in the root bin.
+        assert_eq!(reg2bin(0, 1 << 29), 0);
+    }
+
+    #[test]
+    fn ref_span_counts_only_reference_consuming_ops() {
+        assert_eq!(ref_span("5M1I3M"), 8);
+        assert_eq!(ref_span("10M5D2M"), 17);
+        assert_eq!(ref_span("4S10M4S"), 10);
+        assert_eq!(ref_span("*"), 0);
+    }
+}
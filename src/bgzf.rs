@@ -0,0 +1,304 @@
+//! BGZF (block-gzip) output support.
+//!
+//! BAM files are not plain gzip: they are a concatenation of independent
+//! gzip members, each holding at most 64 KiB of uncompressed data, with an
+//! extra field (`BC`) recording the compressed size of the member. This
+//! lets readers seek directly to a block via a *virtual offset* instead of
+//! decompressing the whole file. See the SAM/BAM spec section 4.1.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+/// Largest amount of uncompressed data packed into a single BGZF block.
+/// Kept below 64 KiB so that even incompressible data, plus the gzip
+/// header and footer, yields a member whose size fits the 16-bit `BSIZE`.
+const MAX_BLOCK_SIZE: usize = 0xff00;
+
+/// Subfield identifier (`BC`) that marks the BGZF extra field.
+const BGZF_SUBFIELD_ID: [u8; 2] = [66, 67];
+
+/// The 28-byte empty BGZF block every conforming file ends with.
+const BGZF_EOF: [u8; 28] = [
+    31, 139, 8, 4, 0, 0, 0, 0,
+    0, 255, 6, 0, 66, 67, 2, 0,
+    27, 0, 3, 0, 0, 0, 0, 0,
+    0, 0, 0, 0,
+];
+
+/// Buffers writes and emits them as a stream of BGZF blocks.
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+    compressed_offset: u64,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        BgzfWriter {
+            inner,
+            buffer: Vec::with_capacity(MAX_BLOCK_SIZE),
+            compressed_offset: 0,
+        }
+    }
+
+    /// The current BGZF virtual offset: `(compressed_offset << 16) |
+    /// offset_within_uncompressed_block`. Callers use this to record where
+    /// a record starts and ends for indexing.
+    pub fn virtual_offset(&self) -> u64 {
+        (self.compressed_offset << 16) | self.buffer.len() as u64
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let block = encode_block(&self.buffer)?;
+        self.inner.write_all(&block)?;
+        self.compressed_offset += block.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Flushes any buffered data, writes the final EOF block, and returns
+    /// the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        self.inner.write_all(&BGZF_EOF)?;
+        Ok(self.inner)
+    }
+}
+
+/// A BGZF writer that can report the virtual offset records should be
+/// indexed at, whether or not that offset is final at the time of the call.
+/// [`BgzfWriter`] returns a real offset immediately; [`ParallelBgzfWriter`]
+/// returns a provisional one that must be remapped once compression
+/// finishes (see [`crate::bai::BaiIndexBuilder::remap_block_offsets`]).
+pub trait BlockWriter: Write {
+    fn block_offset(&self) -> u64;
+}
+
+impl<W: Write> BlockWriter for BgzfWriter<W> {
+    fn block_offset(&self) -> u64 {
+        self.virtual_offset()
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        let mut data = buf;
+        while !data.is_empty() {
+            let space = MAX_BLOCK_SIZE - self.buffer.len();
+            let take = space.min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            written += take;
+            if self.buffer.len() == MAX_BLOCK_SIZE {
+                self.flush_block()?;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_block()?;
+        self.inner.flush()
+    }
+}
+
+/// Compresses `data` into a single BGZF member with the `BC` extra field.
+fn encode_block(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = DeflateEncoder::new(&mut compressed, Compression::default());
+        encoder.write_all(data)?;
+        encoder.finish()?;
+    }
+
+    let mut block = Vec::with_capacity(18 + compressed.len() + 8);
+    block.extend_from_slice(&[31, 139, 8, 4, 0, 0, 0, 0, 0, 255]);
+    block.extend_from_slice(&6u16.to_le_bytes()); // XLEN
+    block.extend_from_slice(&BGZF_SUBFIELD_ID);
+    block.extend_from_slice(&2u16.to_le_bytes()); // SLEN
+    let bsize_at = block.len();
+    block.extend_from_slice(&0u16.to_le_bytes()); // BSIZE placeholder
+    block.extend_from_slice(&compressed);
+    block.extend_from_slice(&crc32(data).to_le_bytes());
+    block.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+    let bsize = (block.len() - 1) as u16;
+    block[bsize_at..bsize_at + 2].copy_from_slice(&bsize.to_le_bytes());
+
+    Ok(block)
+}
+
+/// CRC-32 (IEEE, as used by gzip) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// A block queued for compression, tagged with its submission order so the
+/// writer thread can put finished blocks back in order.
+struct Job {
+    seq: u64,
+    data: Vec<u8>,
+}
+
+/// What the writer thread hands back once every block has been written: the
+/// underlying writer and the real compressed byte offset of each block
+/// (indexed by submission sequence).
+type WriterThreadResult<W> = io::Result<(W, Vec<u64>)>;
+
+/// A `BgzfWriter` that hands each full block to a pool of worker threads
+/// for deflate, while a dedicated writer thread reassembles the compressed
+/// blocks in submission order. Because compression is the only parallel
+/// step, record encoding and `.bai` chunk ordering stay deterministic
+/// regardless of thread count.
+///
+/// Block byte offsets aren't known until a block's compression finishes, so
+/// [`BlockWriter::block_offset`] returns a provisional `(seq << 16) |
+/// local_offset` value during writing. Call [`ParallelBgzfWriter::finish`]
+/// to get back the real per-block compressed offsets and remap any indices
+/// built against the provisional values.
+pub struct ParallelBgzfWriter<W: Write + Send + 'static> {
+    buffer: Vec<u8>,
+    next_seq: u64,
+    job_tx: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    writer_thread: Option<JoinHandle<WriterThreadResult<W>>>,
+}
+
+impl<W: Write + Send + 'static> ParallelBgzfWriter<W> {
+    pub fn new(inner: W, threads: usize) -> Self {
+        let threads = threads.max(1);
+
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let (result_tx, result_rx) = mpsc::channel::<(u64, io::Result<Vec<u8>>)>();
+
+        let workers = (0..threads)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let result_tx = result_tx.clone();
+                thread::spawn(move || loop {
+                    let job = {
+                        let job_rx = job_rx.lock().unwrap();
+                        job_rx.recv()
+                    };
+                    match job {
+                        Ok(job) => {
+                            let block = encode_block(&job.data);
+                            if result_tx.send((job.seq, block)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+        drop(result_tx);
+
+        let writer_thread = thread::spawn(move || -> WriterThreadResult<W> {
+            let mut inner = inner;
+            let mut pending: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+            let mut next_write = 0u64;
+            let mut block_offsets = vec![0u64];
+
+            for (seq, block) in result_rx {
+                pending.insert(seq, block?);
+                while let Some(block) = pending.remove(&next_write) {
+                    inner.write_all(&block)?;
+                    let offset = *block_offsets.last().unwrap() + block.len() as u64;
+                    block_offsets.push(offset);
+                    next_write += 1;
+                }
+            }
+
+            inner.write_all(&BGZF_EOF)?;
+            Ok((inner, block_offsets))
+        });
+
+        ParallelBgzfWriter {
+            buffer: Vec::with_capacity(MAX_BLOCK_SIZE),
+            next_seq: 0,
+            job_tx: Some(job_tx),
+            workers,
+            writer_thread: Some(writer_thread),
+        }
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::replace(&mut self.buffer, Vec::with_capacity(MAX_BLOCK_SIZE));
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.job_tx
+            .as_ref()
+            .unwrap()
+            .send(Job { seq, data })
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "BGZF writer thread exited"))
+    }
+
+    /// Flushes any buffered data, waits for every queued block to finish
+    /// compressing and the final EOF block to be written, then returns the
+    /// underlying writer along with the real compressed offset of every
+    /// block (indexed by submission sequence), for use with
+    /// [`crate::bai::BaiIndexBuilder::remap_block_offsets`].
+    pub fn finish(mut self) -> WriterThreadResult<W> {
+        self.flush_block()?;
+        self.job_tx.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.writer_thread.take().unwrap().join().unwrap()
+    }
+}
+
+impl<W: Write + Send + 'static> BlockWriter for ParallelBgzfWriter<W> {
+    fn block_offset(&self) -> u64 {
+        (self.next_seq << 16) | self.buffer.len() as u64
+    }
+}
+
+impl<W: Write + Send + 'static> Write for ParallelBgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        let mut data = buf;
+        while !data.is_empty() {
+            let space = MAX_BLOCK_SIZE - self.buffer.len();
+            let take = space.min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            written += take;
+            if self.buffer.len() == MAX_BLOCK_SIZE {
+                self.flush_block()?;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_block()
+    }
+}
@@ -0,0 +1,308 @@
+//! BAM→SAM decoding: the reverse of the encoding in `main.rs`, reusing the
+//! shared tables in [`crate::format`] so the two directions stay in sync.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::format::{BAM_MAGIC, CIGAR_OPS, SEQ_NT16_STR};
+
+/// Reads the BGZF-compressed BAM file at `bam_path` and writes the
+/// equivalent SAM text to `sam_path`.
+pub fn decode_bam(bam_path: &str, sam_path: &str) -> io::Result<()> {
+    let file = File::open(bam_path)?;
+    let mut data = Vec::new();
+    MultiGzDecoder::new(file).read_to_end(&mut data)?;
+
+    let mut cursor = 0usize;
+
+    let magic = take(&data, &mut cursor, 4);
+    if magic != BAM_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a BAM file"));
+    }
+
+    let l_text = read_u32(&data, &mut cursor) as usize;
+    let header_text = String::from_utf8_lossy(take(&data, &mut cursor, l_text)).into_owned();
+
+    let n_ref = read_u32(&data, &mut cursor) as usize;
+    let mut ref_names = Vec::with_capacity(n_ref);
+    for _ in 0..n_ref {
+        let l_name = read_u32(&data, &mut cursor) as usize;
+        let name_cstr = take(&data, &mut cursor, l_name);
+        ref_names.push(String::from_utf8_lossy(&name_cstr[..l_name - 1]).into_owned());
+        read_u32(&data, &mut cursor); // l_ref, unused once decoded to text
+    }
+
+    let out_file = File::create(sam_path)?;
+    let mut writer = BufWriter::new(out_file);
+    writer.write_all(header_text.as_bytes())?;
+
+    while cursor < data.len() {
+        let block_size = read_u32(&data, &mut cursor) as usize;
+        let record = take(&data, &mut cursor, block_size);
+        writer.write_all(decode_record(record, &ref_names).as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn decode_record(record: &[u8], ref_names: &[String]) -> String {
+    let mut cursor = 0usize;
+
+    let tid = read_i32(record, &mut cursor);
+    let pos = read_i32(record, &mut cursor);
+    let l_read_name = record[cursor] as usize;
+    cursor += 1;
+    let mapq = record[cursor];
+    cursor += 1;
+    let _bin = read_u16(record, &mut cursor);
+    let n_cigar_op = read_u16(record, &mut cursor) as usize;
+    let flag = read_u16(record, &mut cursor);
+    let l_seq = read_u32(record, &mut cursor) as usize;
+    let next_tid = read_i32(record, &mut cursor);
+    let next_pos = read_i32(record, &mut cursor);
+    let tlen = read_i32(record, &mut cursor);
+
+    let qname_cstr = take(record, &mut cursor, l_read_name);
+    let qname = String::from_utf8_lossy(&qname_cstr[..l_read_name - 1]).into_owned();
+
+    let mut cigar = String::new();
+    for _ in 0..n_cigar_op {
+        let packed = read_u32(record, &mut cursor);
+        let len = packed >> 4;
+        let op = CIGAR_OPS.get((packed & 0xF) as usize).copied().unwrap_or('?');
+        cigar.push_str(&len.to_string());
+        cigar.push(op);
+    }
+    if cigar.is_empty() {
+        cigar.push('*');
+    }
+
+    let seq_bytes = take(record, &mut cursor, l_seq.div_ceil(2));
+    let mut seq = String::with_capacity(l_seq);
+    for i in 0..l_seq {
+        let byte = seq_bytes[i / 2];
+        let code = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+        seq.push(SEQ_NT16_STR[code as usize]);
+    }
+    if seq.is_empty() {
+        seq.push('*');
+    }
+
+    let qual_bytes = take(record, &mut cursor, l_seq);
+    let qual = if qual_bytes.first() == Some(&0xFF) {
+        "*".to_string()
+    } else {
+        qual_bytes.iter().map(|&b| (b + 33) as char).collect()
+    };
+
+    let rname = if tid < 0 {
+        "*".to_string()
+    } else {
+        ref_names[tid as usize].clone()
+    };
+    let rnext = if next_tid < 0 {
+        "*".to_string()
+    } else if next_tid == tid {
+        "=".to_string()
+    } else {
+        ref_names[next_tid as usize].clone()
+    };
+
+    let mut fields = vec![
+        qname,
+        flag.to_string(),
+        rname,
+        (pos + 1).to_string(),
+        mapq.to_string(),
+        cigar,
+        rnext,
+        (next_pos + 1).to_string(),
+        tlen.to_string(),
+        seq,
+        qual,
+    ];
+
+    fields.extend(decode_tags(&record[cursor..]));
+    fields.join("\t")
+}
+
+fn decode_tags(tags: &[u8]) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut cursor = 0usize;
+
+    while cursor + 3 <= tags.len() {
+        let tag = std::str::from_utf8(&tags[cursor..cursor + 2]).unwrap_or("??");
+        let type_char = tags[cursor + 2] as char;
+        cursor += 3;
+
+        let rendered = match type_char {
+            'A' => {
+                let v = tags[cursor] as char;
+                cursor += 1;
+                Some(format!("{}:A:{}", tag, v))
+            }
+            'c' => {
+                let v = tags[cursor] as i8;
+                cursor += 1;
+                Some(format!("{}:i:{}", tag, v))
+            }
+            'C' => {
+                let v = tags[cursor];
+                cursor += 1;
+                Some(format!("{}:i:{}", tag, v))
+            }
+            's' => {
+                let v = i16::from_le_bytes(tags[cursor..cursor + 2].try_into().unwrap());
+                cursor += 2;
+                Some(format!("{}:i:{}", tag, v))
+            }
+            'S' => {
+                let v = u16::from_le_bytes(tags[cursor..cursor + 2].try_into().unwrap());
+                cursor += 2;
+                Some(format!("{}:i:{}", tag, v))
+            }
+            'i' => {
+                let v = i32::from_le_bytes(tags[cursor..cursor + 4].try_into().unwrap());
+                cursor += 4;
+                Some(format!("{}:i:{}", tag, v))
+            }
+            'I' => {
+                let v = u32::from_le_bytes(tags[cursor..cursor + 4].try_into().unwrap());
+                cursor += 4;
+                Some(format!("{}:i:{}", tag, v))
+            }
+            'f' => {
+                let v = f32::from_le_bytes(tags[cursor..cursor + 4].try_into().unwrap());
+                cursor += 4;
+                Some(format!("{}:f:{}", tag, v))
+            }
+            'Z' | 'H' => {
+                let start = cursor;
+                while tags[cursor] != 0 {
+                    cursor += 1;
+                }
+                let s = std::str::from_utf8(&tags[start..cursor]).unwrap_or("");
+                cursor += 1; // NUL terminator
+                Some(format!("{}:{}:{}", tag, type_char, s))
+            }
+            'B' => {
+                let subtype = tags[cursor] as char;
+                cursor += 1;
+                let count = u32::from_le_bytes(tags[cursor..cursor + 4].try_into().unwrap()) as usize;
+                cursor += 4;
+                let mut elems = Vec::with_capacity(count);
+                for _ in 0..count {
+                    elems.push(decode_b_element(tags, &mut cursor, subtype));
+                }
+                Some(format!("{}:B:{},{}", tag, subtype, elems.join(",")))
+            }
+            _ => None,
+        };
+
+        match rendered {
+            Some(text) => result.push(text),
+            None => break,
+        }
+    }
+
+    result
+}
+
+fn decode_b_element(tags: &[u8], cursor: &mut usize, subtype: char) -> String {
+    match subtype {
+        'c' => {
+            let v = tags[*cursor] as i8;
+            *cursor += 1;
+            v.to_string()
+        }
+        'C' => {
+            let v = tags[*cursor];
+            *cursor += 1;
+            v.to_string()
+        }
+        's' => {
+            let v = i16::from_le_bytes(tags[*cursor..*cursor + 2].try_into().unwrap());
+            *cursor += 2;
+            v.to_string()
+        }
+        'S' => {
+            let v = u16::from_le_bytes(tags[*cursor..*cursor + 2].try_into().unwrap());
+            *cursor += 2;
+            v.to_string()
+        }
+        'i' => {
+            let v = i32::from_le_bytes(tags[*cursor..*cursor + 4].try_into().unwrap());
+            *cursor += 4;
+            v.to_string()
+        }
+        'I' => {
+            let v = u32::from_le_bytes(tags[*cursor..*cursor + 4].try_into().unwrap());
+            *cursor += 4;
+            v.to_string()
+        }
+        'f' => {
+            let v = f32::from_le_bytes(tags[*cursor..*cursor + 4].try_into().unwrap());
+            *cursor += 4;
+            v.to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+fn take<'a>(data: &'a [u8], cursor: &mut usize, len: usize) -> &'a [u8] {
+    let slice = &data[*cursor..*cursor + len];
+    *cursor += len;
+    slice
+}
+
+fn read_u32(data: &[u8], cursor: &mut usize) -> u32 {
+    let v = u32::from_le_bytes(data[*cursor..*cursor + 4].try_into().unwrap());
+    *cursor += 4;
+    v
+}
+
+fn read_i32(data: &[u8], cursor: &mut usize) -> i32 {
+    let v = i32::from_le_bytes(data[*cursor..*cursor + 4].try_into().unwrap());
+    *cursor += 4;
+    v
+}
+
+fn read_u16(data: &[u8], cursor: &mut usize) -> u16 {
+    let v = u16::from_le_bytes(data[*cursor..*cursor + 2].try_into().unwrap());
+    *cursor += 2;
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sam_to_bam_to_sam_round_trip() {
+        let dir = std::env::temp_dir();
+        let sam_in = dir.join("sam_to_bam_roundtrip_in.sam");
+        let bam_out = dir.join("sam_to_bam_roundtrip.bam");
+        let sam_out = dir.join("sam_to_bam_roundtrip_out.sam");
+
+        let sam_text = "@HD\tVN:1.6\tSO:coordinate\n\
+@SQ\tSN:chr1\tLN:248956422\n\
+read1\t0\tchr1\t100\t60\t5M1I3M\t=\t150\t50\tACGTACGTA\tIIIIIIIII\tNM:i:1\tAS:f:12.5\tBC:B:i,1,2,3\n";
+        std::fs::write(&sam_in, sam_text).unwrap();
+
+        crate::sam_to_bam(sam_in.to_str().unwrap(), bam_out.to_str().unwrap(), 1).unwrap();
+        decode_bam(bam_out.to_str().unwrap(), sam_out.to_str().unwrap()).unwrap();
+
+        let decoded = std::fs::read_to_string(&sam_out).unwrap();
+        let decoded_lines: Vec<&str> = decoded.lines().collect();
+        let original_lines: Vec<&str> = sam_text.lines().collect();
+        assert_eq!(decoded_lines, original_lines);
+
+        let _ = std::fs::remove_file(&sam_in);
+        let _ = std::fs::remove_file(&bam_out);
+        let _ = std::fs::remove_file(format!("{}.bai", bam_out.to_str().unwrap()));
+        let _ = std::fs::remove_file(&sam_out);
+    }
+}
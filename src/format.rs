@@ -0,0 +1,28 @@
+//! Constants shared between the SAM→BAM encoder and the BAM→SAM decoder,
+//! kept in one place so the two directions can't drift out of sync.
+
+/// The 4-byte magic every BAM file starts with.
+pub const BAM_MAGIC: &[u8] = b"BAM\x01";
+
+/// CIGAR operation letters, indexed by their packed BAM op code (0-8).
+pub const CIGAR_OPS: [char; 9] = ['M', 'I', 'D', 'N', 'S', 'H', 'P', '=', 'X'];
+
+/// IUPAC bases, indexed by their packed 4-bit BAM sequence code (0-15).
+pub const SEQ_NT16_STR: [char; 16] = [
+    '=', 'A', 'C', 'M', 'G', 'R', 'S', 'V', 'T', 'W', 'Y', 'H', 'K', 'D', 'B', 'N',
+];
+
+/// Looks up the BAM op code for a CIGAR operation letter, defaulting to the
+/// "unassigned" code used for anything outside `MIDNSHP=X`.
+pub fn cigar_op_code(op: char) -> u8 {
+    CIGAR_OPS.iter().position(|&c| c == op).unwrap_or(15) as u8
+}
+
+/// Looks up the packed 4-bit BAM sequence code for an IUPAC base, defaulting
+/// to `N` for anything unrecognized.
+pub fn seq_base_code(base: char) -> u8 {
+    SEQ_NT16_STR
+        .iter()
+        .position(|&c| c == base.to_ascii_uppercase())
+        .unwrap_or(15) as u8
+}
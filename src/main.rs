@@ -1,37 +1,66 @@
+mod bai;
+mod bgzf;
+mod decode;
+mod format;
+
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
-use flate2::Compression;
-use flate2::write::GzEncoder;
 
-const BAM_MAGIC: &[u8] = b"BAM\x01";
-const BAM_EOF: [u8; 28] = [
-    31, 139, 8, 4, 0, 0, 0, 0,
-    0, 255, 6, 0, 66, 67, 2, 0,
-    27, 0, 3, 0, 0, 0, 0, 0,
-    0, 0, 0, 0,
-];
+use bai::BaiIndexBuilder;
+use bgzf::{BgzfWriter, BlockWriter, ParallelBgzfWriter};
+use format::BAM_MAGIC;
 
 fn main() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let threads = extract_threads_flag(&mut args);
+
+    if args.len() == 4 && args[1] == "--decode" {
+        decode::decode_bam(&args[2], &args[3])?;
+        println!("✅ SAM written to {}", args[3]);
+        return Ok(());
+    }
+
     if args.len() != 3 {
-        eprintln!("Usage: {} <input.sam> <output.bam>", args[0]);
+        eprintln!("Usage: {} <input.sam> <output.bam> [--threads N]", args[0]);
+        eprintln!("       {} --decode <input.bam> <output.sam>", args[0]);
         std::process::exit(1);
     }
 
-    let sam_file = File::open(&args[1])?;
-    let reader = BufReader::new(sam_file);
+    sam_to_bam(&args[1], &args[2], threads)?;
+    println!("✅ BAM written to {}", args[2]);
+    Ok(())
+}
+
+/// Scans `args` for a `--threads N` flag, removing it in place and
+/// returning the parsed thread count (default `1`, i.e. the single-threaded
+/// `BgzfWriter` path).
+fn extract_threads_flag(args: &mut Vec<String>) -> usize {
+    let mut threads = 1;
+    if let Some(pos) = args.iter().position(|a| a == "--threads") {
+        if let Some(value) = args.get(pos + 1) {
+            threads = value.parse().unwrap_or(1);
+        }
+        args.drain(pos..(pos + 2).min(args.len()));
+    }
+    threads
+}
 
-    let bam_file = File::create(&args[2])?;
-    let mut bgzf_writer = GzEncoder::new(bam_file, Compression::default());
+fn sam_to_bam(sam_path: &str, bam_path: &str, threads: usize) -> std::io::Result<()> {
+    let sam_file = File::open(sam_path)?;
+    let reader = BufReader::new(sam_file);
 
-    // Parse header
+    // SAM headers always precede alignment records, so buffer only the
+    // header lines; the first non-header line is the start of the record
+    // stream and is handed off to the second pass below.
     let mut header_text = Vec::new();
     let mut ref_names = Vec::new();
     let mut ref_lengths = Vec::new();
-    let mut records = Vec::new();
 
-    for line in reader.lines() {
+    let mut lines = reader.lines();
+    let mut first_record = None;
+
+    for line in &mut lines {
         let line = line?;
         if line.starts_with('@') {
             header_text.extend_from_slice(line.as_bytes());
@@ -53,139 +82,260 @@ fn main() -> std::io::Result<()> {
                 }
             }
         } else {
-            records.push(line);
+            first_record = Some(line);
+            break;
         }
     }
 
-    // Write BAM magic
-    bgzf_writer.write_all(BAM_MAGIC)?;
+    let bam_file = File::create(bam_path)?;
+    let bai_path = format!("{}.bai", bam_path);
+
+    if threads > 1 {
+        let mut writer = ParallelBgzfWriter::new(bam_file, threads);
+        let mut bai_index = BaiIndexBuilder::new(ref_names.len());
+        write_bam_body(
+            &mut writer,
+            &header_text,
+            &ref_names,
+            &ref_lengths,
+            first_record,
+            lines,
+            &mut bai_index,
+        )?;
+        let (mut inner, block_offsets) = writer.finish()?;
+        inner.flush()?;
+        bai_index.remap_block_offsets(&block_offsets);
+        bai_index.write(&bai_path)?;
+    } else {
+        let mut writer = BgzfWriter::new(bam_file);
+        let mut bai_index = BaiIndexBuilder::new(ref_names.len());
+        write_bam_body(
+            &mut writer,
+            &header_text,
+            &ref_names,
+            &ref_lengths,
+            first_record,
+            lines,
+            &mut bai_index,
+        )?;
+        let mut inner = writer.finish()?;
+        inner.flush()?;
+        bai_index.write(&bai_path)?;
+    }
+
+    Ok(())
+}
 
-    // Write header text
-    bgzf_writer.write_all(&(header_text.len() as u32).to_le_bytes())?;
-    bgzf_writer.write_all(&header_text)?;
+/// Writes the BAM magic, header, reference list, and every alignment record
+/// to `writer`, updating `bai_index` as it goes. Shared by the
+/// single-threaded and multithreaded compression paths; `writer` only needs
+/// to be able to report a (possibly provisional) block offset.
+#[allow(clippy::too_many_arguments)]
+fn write_bam_body<W: BlockWriter>(
+    writer: &mut W,
+    header_text: &[u8],
+    ref_names: &[String],
+    ref_lengths: &[u32],
+    first_record: Option<String>,
+    lines: std::io::Lines<BufReader<File>>,
+    bai_index: &mut BaiIndexBuilder,
+) -> std::io::Result<()> {
+    writer.write_all(BAM_MAGIC)?;
 
-    // Write reference sequences
-    bgzf_writer.write_all(&(ref_names.len() as u32).to_le_bytes())?;
+    writer.write_all(&(header_text.len() as u32).to_le_bytes())?;
+    writer.write_all(header_text)?;
+
+    writer.write_all(&(ref_names.len() as u32).to_le_bytes())?;
     for (name, len) in ref_names.iter().zip(ref_lengths.iter()) {
         let name_cstr = format!("{}\0", name);
-        bgzf_writer.write_all(&(name_cstr.len() as u32).to_le_bytes())?;
-        bgzf_writer.write_all(name_cstr.as_bytes())?;
-        bgzf_writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&(name_cstr.len() as u32).to_le_bytes())?;
+        writer.write_all(name_cstr.as_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+    }
+
+    // Encode and write each record as it is read, so peak memory is bounded
+    // by one BGZF block rather than the whole input.
+    if let Some(line) = first_record {
+        write_record(&line, ref_names, writer, bai_index)?;
+    }
+    for line in lines {
+        write_record(&line?, ref_names, writer, bai_index)?;
     }
 
-    // Write dummy alignment records (for demonstration)
-    for record in records {
-        let fields: Vec<&str> = record.split('\t').collect();
-        if fields.len() < 11 {
+    Ok(())
+}
+
+/// Encodes one SAM record and writes it to `writer`, updating `bai_index`
+/// with the (possibly provisional) offset range it occupies.
+fn write_record<W: BlockWriter>(
+    line: &str,
+    ref_names: &[String],
+    writer: &mut W,
+    bai_index: &mut BaiIndexBuilder,
+) -> std::io::Result<()> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 11 {
+        return Ok(());
+    }
+
+    let rname = fields[2];
+    let pos: i32 = fields[3].parse().unwrap_or(1) - 1;
+    let cigar = fields[5];
+    let tid = if rname == "*" {
+        None
+    } else {
+        ref_names.iter().position(|r| r == rname)
+    };
+    let span = bai::ref_span(cigar);
+
+    let voffset_beg = writer.block_offset();
+    let record = encode_record(&fields, ref_names);
+    writer.write_all(&record)?;
+    let voffset_end = writer.block_offset();
+
+    // A record only has a real bin/linear-index entry when its RNAME
+    // resolves to a reference in the dictionary and POS is non-negative
+    // (SAM POS of 0 means "no coordinate"); anything else is unmapped as
+    // far as the index is concerned, matching RNAME `*`.
+    match tid {
+        Some(tid) if pos >= 0 => {
+            bai_index.add_record(tid, pos, span, voffset_beg, voffset_end);
+        }
+        _ => bai_index.add_unmapped(),
+    }
+
+    Ok(())
+}
+
+/// Encodes a single SAM record's fields into its BAM binary form, including
+/// the leading `block_size` prefix.
+fn encode_record(fields: &[&str], ref_names: &[String]) -> Vec<u8> {
+    let qname = fields[0];
+    let flag: u16 = fields[1].parse().unwrap_or(0);
+    let rname = fields[2];
+    let pos: i32 = fields[3].parse().unwrap_or(1) - 1;
+    let mapq: u8 = fields[4].parse().unwrap_or(255);
+    let cigar = fields[5];
+    let rnext = fields[6];
+    let pnext: i32 = fields[7].parse().unwrap_or(1) - 1;
+    let tlen: i32 = fields[8].parse().unwrap_or(0);
+    let seq = fields[9];
+    let qual = fields[10];
+
+    let tid = if rname == "*" {
+        -1
+    } else {
+        ref_names.iter().position(|r| r == rname).map_or(-1, |i| i as i32)
+    };
+    let next_tid = if rnext == "*" {
+        -1
+    } else if rnext == "=" {
+        tid
+    } else {
+        ref_names.iter().position(|r| r == rnext).map_or(-1, |i| i as i32)
+    };
+
+    let l_read_name = qname.len() + 1;
+    let n_cigar_op = cigar.matches(|c: char| c.is_ascii_alphabetic()).count() as u16;
+    let l_seq = seq.len();
+    let span = bai::ref_span(cigar);
+    let bin = if tid >= 0 {
+        bai::reg2bin(pos, pos + span.max(1) as i32)
+    } else {
+        0u16
+    };
+
+    let mut record = Vec::new();
+    record.extend_from_slice(&[0u8; 4]); // placeholder for block_size
+
+    record.extend_from_slice(&(tid as i32).to_le_bytes());
+    record.extend_from_slice(&pos.to_le_bytes());
+    record.push(l_read_name as u8);
+    record.push(mapq);
+    record.extend_from_slice(&bin.to_le_bytes());
+    record.extend_from_slice(&n_cigar_op.to_le_bytes());
+    record.extend_from_slice(&flag.to_le_bytes());
+    record.extend_from_slice(&(l_seq as u32).to_le_bytes());
+    record.extend_from_slice(&(next_tid as i32).to_le_bytes());
+    record.extend_from_slice(&pnext.to_le_bytes());
+    record.extend_from_slice(&tlen.to_le_bytes());
+
+    record.extend_from_slice(qname.as_bytes());
+    record.push(0); // null terminator for read name
+
+    let cigar_bytes = encode_cigar(cigar);
+    record.extend_from_slice(&cigar_bytes);
+
+    let seq_bytes = encode_seq(seq);
+    record.extend_from_slice(&seq_bytes);
+
+    let qual_bytes = encode_qual(qual);
+    record.extend_from_slice(&qual_bytes);
+
+    for tag_field in fields.iter().skip(11) {
+        let parts: Vec<&str> = tag_field.splitn(3, ':').collect();
+        if parts.len() != 3 {
+            eprintln!("⚠️ Skipping malformed tag '{}'", tag_field);
             continue;
         }
 
-        let qname = fields[0];
-        let flag: u16 = fields[1].parse().unwrap_or(0);
-        let rname = fields[2];
-        let pos: i32 = fields[3].parse().unwrap_or(1) - 1;
-        let mapq: u8 = fields[4].parse().unwrap_or(255);
-        let cigar = fields[5];
-        let rnext = fields[6];
-        let pnext: i32 = fields[7].parse().unwrap_or(1) - 1;
-        let tlen: i32 = fields[8].parse().unwrap_or(0);
-        let seq = fields[9];
-        let qual = fields[10];
-
-        let tid = ref_names.iter().position(|r| r == rname).unwrap_or(0) as i32;
-        let next_tid = if rnext == "*" {
-            -1
-        } else if rnext == "=" {
-            tid
-        } else {
-            ref_names.iter().position(|r| r == rnext).unwrap_or(0) as i32
-        };
-
-        let l_read_name = qname.len() + 1;
-        let n_cigar_op = cigar.matches(|c: char| c.is_ascii_alphabetic()).count() as u16;
-        let l_seq = seq.len();
-        let bin = 0u16;
-
-        let mut record = Vec::new();
-        record.extend_from_slice(&[0u8; 4]); // placeholder for block_size
-
-        record.extend_from_slice(&(tid as i32).to_le_bytes());
-        record.extend_from_slice(&pos.to_le_bytes());
-        record.push(l_read_name as u8);
-        record.push(mapq);
-        record.extend_from_slice(&bin.to_le_bytes());
-        record.extend_from_slice(&n_cigar_op.to_le_bytes());
-        record.extend_from_slice(&flag.to_le_bytes());
-        record.extend_from_slice(&(l_seq as u32).to_le_bytes());
-        record.extend_from_slice(&(next_tid as i32).to_le_bytes());
-        record.extend_from_slice(&pnext.to_le_bytes());
-        record.extend_from_slice(&tlen.to_le_bytes());
-
-        record.extend_from_slice(qname.as_bytes());
-        record.push(0); // null terminator for read name
-
-        let cigar_bytes = encode_cigar(cigar);
-        record.extend_from_slice(&cigar_bytes);
-
-        let seq_bytes = encode_seq(seq);
-        record.extend_from_slice(&seq_bytes);
-
-        let qual_bytes = encode_qual(qual);
-        record.extend_from_slice(&qual_bytes);
-
-        for tag_field in fields.iter().skip(11) {
-            let parts: Vec<&str> = tag_field.splitn(3, ':').collect();
-            if parts.len() != 3 {
-                eprintln!("⚠️ Skipping malformed tag '{}'", tag_field);
-                continue;
+        let tag = parts[0];
+        let type_char = parts[1];
+        let value = parts[2];
+
+        match type_char {
+            "A" => {
+                record.extend_from_slice(tag.as_bytes());
+                record.extend_from_slice(b"A");
+                record.push(value.as_bytes()[0]);
             }
-        
-            let tag = parts[0];
-            let type_char = parts[1];
-            let value = parts[2];
-        
-            match type_char {
-                "A" => {
+            "i" => {
+                if let Ok(val) = value.parse::<i64>() {
                     record.extend_from_slice(tag.as_bytes());
-                    record.extend_from_slice(b"A");
-                    record.push(value.as_bytes()[0]);
-                }
-                "i" => {
-                    if let Ok(val) = value.parse::<i32>() {
-                        record.extend_from_slice(tag.as_bytes());
-                        record.extend_from_slice(b"i");
-                        record.extend_from_slice(&val.to_le_bytes());
-                    } else {
-                        eprintln!("⚠️ Could not parse integer tag '{}'", tag_field);
-                    }
+                    record.extend_from_slice(&encode_int_tag(val));
+                } else {
+                    eprintln!("⚠️ Could not parse integer tag '{}'", tag_field);
                 }
-                "Z" => {
+            }
+            "Z" => {
+                record.extend_from_slice(tag.as_bytes());
+                record.extend_from_slice(b"Z");
+                record.extend_from_slice(value.as_bytes());
+                record.push(0);
+            }
+            "f" => {
+                if let Ok(val) = value.parse::<f32>() {
                     record.extend_from_slice(tag.as_bytes());
-                    record.extend_from_slice(b"Z");
-                    record.extend_from_slice(value.as_bytes());
-                    record.push(0);
-                }
-                "f" | "B" => {
-                    eprintln!("⚠️ Skipping unsupported tag type '{}' on record {}: {}", type_char, qname, tag_field);
+                    record.extend_from_slice(b"f");
+                    record.extend_from_slice(&val.to_le_bytes());
+                } else {
+                    eprintln!("⚠️ Could not parse float tag '{}'", tag_field);
                 }
-                _ => {
-                    eprintln!("⚠️ Unknown tag type '{}' in record {}: {}", type_char, qname, tag_field);
+            }
+            "H" => {
+                record.extend_from_slice(tag.as_bytes());
+                record.extend_from_slice(b"H");
+                record.extend_from_slice(value.as_bytes());
+                record.push(0);
+            }
+            "B" => {
+                if let Some(bytes) = encode_b_tag(value) {
+                    record.extend_from_slice(tag.as_bytes());
+                    record.extend_from_slice(b"B");
+                    record.extend_from_slice(&bytes);
+                } else {
+                    eprintln!("⚠️ Could not parse array tag '{}'", tag_field);
                 }
             }
-        }        
-
-        let block_size = (record.len() - 4) as u32;
-        record[0..4].copy_from_slice(&block_size.to_le_bytes());
-        bgzf_writer.write_all(&record)?;
+            _ => {
+                eprintln!("⚠️ Unknown tag type '{}' in record {}: {}", type_char, qname, tag_field);
+            }
+        }
     }
 
-    // Finish BGZF
-    let mut writer = bgzf_writer.finish()?;
-    writer.write_all(&BAM_EOF)?;
-    writer.flush()?;
-
-    println!("✅ BAM written to {}", args[2]);
-    Ok(())
+    let block_size = (record.len() - 4) as u32;
+    record[0..4].copy_from_slice(&block_size.to_le_bytes());
+    record
 }
 
 fn encode_cigar(cigar: &str) -> Vec<u8> {
@@ -196,10 +346,7 @@ fn encode_cigar(cigar: &str) -> Vec<u8> {
             num.push(c);
         } else {
             let len: u32 = num.parse().unwrap_or(0);
-            let op_code = match c {
-                'M' => 0, 'I' => 1, 'D' => 2, 'N' => 3, 'S' => 4,
-                'H' => 5, 'P' => 6, '=' => 7, 'X' => 8, _ => 15,
-            };
+            let op_code = format::cigar_op_code(c);
             let encoded = (len << 4) | (op_code as u32);
             result.extend_from_slice(&encoded.to_le_bytes());
             num.clear();
@@ -212,13 +359,7 @@ fn encode_seq(seq: &str) -> Vec<u8> {
     let mut result = Vec::new();
     let mut byte = 0u8;
     for (i, base) in seq.chars().enumerate() {
-        let code = match base.to_ascii_uppercase() {
-            '=' => 0, 'A' => 1, 'C' => 2, 'M' => 3,
-            'G' => 4, 'R' => 5, 'S' => 6, 'V' => 7,
-            'T' => 8, 'W' => 9, 'Y' => 10, 'H' => 11,
-            'K' => 12, 'D' => 13, 'B' => 14, 'N' => 15,
-            _ => 15,
-        };
+        let code = format::seq_base_code(base);
         if i % 2 == 0 {
             byte = code << 4;
         } else {
@@ -240,3 +381,146 @@ fn encode_qual(qual: &str) -> Vec<u8> {
         qual.bytes().map(|b| b.saturating_sub(33)).collect()
     }
 }
+
+/// Encodes an integer tag value as the smallest BAM integer type that fits
+/// it (`c`/`C`/`s`/`S`/`i`/`I`), matching htslib's choice of width.
+fn encode_int_tag(val: i64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(5);
+    if let Ok(v) = i8::try_from(val) {
+        bytes.push(b'c');
+        bytes.extend_from_slice(&v.to_le_bytes());
+    } else if let Ok(v) = u8::try_from(val) {
+        bytes.push(b'C');
+        bytes.extend_from_slice(&v.to_le_bytes());
+    } else if let Ok(v) = i16::try_from(val) {
+        bytes.push(b's');
+        bytes.extend_from_slice(&v.to_le_bytes());
+    } else if let Ok(v) = u16::try_from(val) {
+        bytes.push(b'S');
+        bytes.extend_from_slice(&v.to_le_bytes());
+    } else if let Ok(v) = i32::try_from(val) {
+        bytes.push(b'i');
+        bytes.extend_from_slice(&v.to_le_bytes());
+    } else {
+        bytes.push(b'I');
+        bytes.extend_from_slice(&(val as u32).to_le_bytes());
+    }
+    bytes
+}
+
+/// Encodes a `B` array tag body: a subtype byte, a little-endian `uint32`
+/// element count, then each element in the subtype's native width.
+fn encode_b_tag(value: &str) -> Option<Vec<u8>> {
+    let mut parts = value.split(',');
+    let subtype = parts.next()?;
+    let elements: Vec<&str> = parts.collect();
+
+    let mut bytes = Vec::new();
+    bytes.push(subtype.as_bytes().first().copied()?);
+    bytes.extend_from_slice(&(elements.len() as u32).to_le_bytes());
+
+    for elem in elements {
+        match subtype {
+            "c" => bytes.extend_from_slice(&elem.parse::<i8>().ok()?.to_le_bytes()),
+            "C" => bytes.extend_from_slice(&elem.parse::<u8>().ok()?.to_le_bytes()),
+            "s" => bytes.extend_from_slice(&elem.parse::<i16>().ok()?.to_le_bytes()),
+            "S" => bytes.extend_from_slice(&elem.parse::<u16>().ok()?.to_le_bytes()),
+            "i" => bytes.extend_from_slice(&elem.parse::<i32>().ok()?.to_le_bytes()),
+            "I" => bytes.extend_from_slice(&elem.parse::<u32>().ok()?.to_le_bytes()),
+            "f" => bytes.extend_from_slice(&elem.parse::<f32>().ok()?.to_le_bytes()),
+            _ => return None,
+        }
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a single record with one optional tag field and decodes it
+    /// back, returning just that tag's rendered `TAG:TYPE:VALUE` text.
+    fn round_trip_tag(tag_field: &str) -> String {
+        let ref_names = vec!["chr1".to_string()];
+        let fields = vec![
+            "read1", "0", "chr1", "100", "60", "5M", "=", "150", "50", "ACGTA", "IIIII",
+            tag_field,
+        ];
+        let record = encode_record(&fields, &ref_names);
+        let decoded = decode::decode_record(&record[4..], &ref_names);
+        decoded.split('\t').next_back().unwrap().to_string()
+    }
+
+    /// Encodes a single record against `ref_names` and decodes it back,
+    /// returning just the rendered `RNAME` field.
+    fn round_trip_rname(ref_names: &[String], rname: &str) -> String {
+        let fields = vec![
+            "read1", "0", rname, "50", "60", "5M", "*", "0", "0", "ACGTA", "IIIII",
+        ];
+        let record = encode_record(&fields, ref_names);
+        let decoded = decode::decode_record(&record[4..], ref_names);
+        decoded.split('\t').nth(2).unwrap().to_string()
+    }
+
+    #[test]
+    fn encode_record_treats_unresolvable_rname_as_unmapped() {
+        let ref_names = vec!["chr1".to_string()];
+        assert_eq!(round_trip_rname(&ref_names, "chrBOGUS"), "*");
+    }
+
+    #[test]
+    fn encode_record_treats_rname_as_unmapped_with_no_sq_lines() {
+        let ref_names: Vec<String> = Vec::new();
+        assert_eq!(round_trip_rname(&ref_names, "chr1"), "*");
+    }
+
+    #[test]
+    fn encode_record_picks_smallest_int_tag_width() {
+        assert_eq!(round_trip_tag("NM:i:5"), "NM:i:5"); // c
+        assert_eq!(round_trip_tag("NM:i:200"), "NM:i:200"); // C
+        assert_eq!(round_trip_tag("NM:i:-200"), "NM:i:-200"); // s
+        assert_eq!(round_trip_tag("NM:i:40000"), "NM:i:40000"); // S
+        assert_eq!(round_trip_tag("NM:i:-70000"), "NM:i:-70000"); // i
+        assert_eq!(round_trip_tag("NM:i:3000000000"), "NM:i:3000000000"); // I
+    }
+
+    #[test]
+    fn encode_record_b_array_tag() {
+        assert_eq!(round_trip_tag("BC:B:i,1,2,3"), "BC:B:i,1,2,3");
+        assert_eq!(round_trip_tag("BC:B:f,1.5,-2.5"), "BC:B:f,1.5,-2.5");
+    }
+
+    #[test]
+    fn encode_record_hex_tag() {
+        assert_eq!(round_trip_tag("HX:H:1a2bff"), "HX:H:1a2bff");
+    }
+
+    #[test]
+    fn encode_cigar_packs_length_and_op_code() {
+        let encoded = encode_cigar("5M1I3M");
+        assert_eq!(encoded.len(), 3 * 4);
+        assert_eq!(u32::from_le_bytes(encoded[0..4].try_into().unwrap()), 5 << 4); // op 0 = M
+        assert_eq!(u32::from_le_bytes(encoded[4..8].try_into().unwrap()), (1 << 4) | 1);
+    }
+
+    #[test]
+    fn encode_seq_packs_two_bases_per_byte() {
+        // A=1, C=2, G=4, T=8 in the BAM 4-bit nucleotide code.
+        assert_eq!(encode_seq("ACGT"), vec![0x12, 0x48]);
+        assert_eq!(encode_seq("ACG"), vec![0x12, 0x40]);
+    }
+
+    #[test]
+    fn encode_int_tag_picks_smallest_width() {
+        assert_eq!(encode_int_tag(5), vec![b'c', 5]);
+        assert_eq!(encode_int_tag(200), [&[b'C'][..], &200u8.to_le_bytes()].concat());
+        assert_eq!(encode_int_tag(-200), [&[b's'][..], &(-200i16).to_le_bytes()].concat());
+        assert_eq!(encode_int_tag(3_000_000_000), [&[b'I'][..], &3_000_000_000u32.to_le_bytes()].concat());
+    }
+
+    #[test]
+    fn encode_b_tag_rejects_unknown_subtype() {
+        assert_eq!(encode_b_tag("q,1,2"), None);
+    }
+}